@@ -4,11 +4,13 @@
 mod thumb_protocol;
 mod utils;
 
-use crate::thumb_protocol::ThumbnailCacheState;
-use crate::utils::{get_hash_filename, get_temp_dir};
+use crate::thumb_protocol::{ThumbnailCacheState, DEFAULT_MAX_CACHE_BYTES};
+use crate::utils::{get_hash_filename, get_temp_dir, get_thumb_cache_dir};
 use base64::{engine::general_purpose, Engine as _};
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
 use std::time::Instant;
 use tauri::{AppHandle, Manager};
 
@@ -133,6 +135,226 @@ async fn commit_assets(
     Ok(new_paths)
 }
 
+#[derive(serde::Serialize)]
+struct CacheStats {
+    total_bytes: u64,
+    file_count: usize,
+    max_bytes: u64,
+}
+
+#[tauri::command]
+async fn get_cache_stats(
+    app: AppHandle,
+    state: tauri::State<'_, ThumbnailCacheState>,
+) -> Result<CacheStats, String> {
+    let thumb_dir = get_thumb_cache_dir(&app);
+    let (total_bytes, file_count) = thumb_protocol::get_cache_dir_stats(&thumb_dir);
+    Ok(CacheStats {
+        total_bytes,
+        file_count,
+        max_bytes: state.max_cache_bytes(),
+    })
+}
+
+// 运行时调整缩略图缓存上限（字节），下一次写入/淘汰时生效
+#[tauri::command]
+async fn set_max_cache_bytes(
+    max_bytes: u64,
+    state: tauri::State<'_, ThumbnailCacheState>,
+) -> Result<(), String> {
+    if max_bytes == 0 {
+        return Err("max_bytes 必须大于 0".to_string());
+    }
+    state.set_max_cache_bytes(max_bytes);
+    Ok(())
+}
+
+#[tauri::command]
+async fn clear_thumb_cache(app: AppHandle) -> Result<(), String> {
+    let thumb_dir = get_thumb_cache_dir(&app);
+    if thumb_dir.exists() {
+        fs::remove_dir_all(&thumb_dir).map_err(|e| e.to_string())?;
+        fs::create_dir_all(&thumb_dir).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+// dHash 感知哈希：缩放为 9x8 灰度图，逐行比较相邻像素明暗得到 64bit 指纹
+fn compute_dhash(path: &Path) -> Option<u64> {
+    let img = image::open(path).ok()?;
+    let gray = img
+        .resize_exact(9, 8, image::imageops::FilterType::Triangle)
+        .to_luma8();
+
+    let mut hash: u64 = 0;
+    let mut bit = 0;
+    for y in 0..8 {
+        for x in 0..8 {
+            let left = gray.get_pixel(x, y)[0];
+            let right = gray.get_pixel(x + 1, y)[0];
+            if left > right {
+                hash |= 1 << bit;
+            }
+            bit += 1;
+        }
+    }
+    Some(hash)
+}
+
+fn find_root(parent: &mut [usize], x: usize) -> usize {
+    if parent[x] != x {
+        parent[x] = find_root(parent, parent[x]);
+    }
+    parent[x]
+}
+
+// 基于 dHash 汉明距离对 assets 目录下的文件做并查集聚类，找出视觉相似的"近似重复"资源
+#[tauri::command]
+async fn find_similar_assets(
+    project_root: String,
+    threshold: Option<u32>,
+) -> Result<Vec<Vec<String>>, String> {
+    let assets_dir = Path::new(&project_root).join("assets");
+    if !assets_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let entries: Vec<PathBuf> = fs::read_dir(&assets_dir)
+        .map_err(|e| e.to_string())?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_file())
+        .collect();
+
+    // 用独立线程池并行计算每个文件的哈希，不能复用 state.pool：
+    // 那是缩略图渲染池，扫描大量 assets 会把它占满，卡住正在进行的缩略图请求
+    let scan_pool = threadpool::ThreadPool::new(num_cpus::get());
+    let (tx, rx) = mpsc::channel();
+    for path in entries {
+        let tx = tx.clone();
+        scan_pool.execute(move || {
+            let hash = compute_dhash(&path);
+            let _ = tx.send((path, hash));
+        });
+    }
+    drop(tx);
+
+    // 不依赖"正好收到 total 条"：某个任务 panic 导致其 tx 克隆提前丢弃也没关系，
+    // channel 在所有发送端都销毁后自然关闭，iter() 照样能结束
+    let hashes: Vec<(PathBuf, u64)> = rx
+        .iter()
+        .filter_map(|(path, hash)| hash.map(|h| (path, h)))
+        .collect();
+
+    let threshold = threshold.unwrap_or(5);
+    let n = hashes.len();
+    let mut parent: Vec<usize> = (0..n).collect();
+    for i in 0..n {
+        for j in (i + 1)..n {
+            if (hashes[i].1 ^ hashes[j].1).count_ones() <= threshold {
+                let (ri, rj) = (find_root(&mut parent, i), find_root(&mut parent, j));
+                if ri != rj {
+                    parent[ri] = rj;
+                }
+            }
+        }
+    }
+
+    let mut clusters: HashMap<usize, Vec<String>> = HashMap::new();
+    for i in 0..n {
+        let root = find_root(&mut parent, i);
+        let filename = hashes[i]
+            .0
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        clusters
+            .entry(root)
+            .or_default()
+            .push(format!("assets/{}", filename));
+    }
+
+    Ok(clusters.into_values().filter(|v| v.len() > 1).collect())
+}
+
+#[derive(serde::Serialize)]
+struct GcResult {
+    removed: Vec<String>,
+    bytes_freed: u64,
+}
+
+// 回收不再被任何文档引用的 assets 文件；move_to_trash 为 false 时才会直接删除，默认先挪到 `_trash` 子目录
+#[tauri::command]
+async fn gc_assets(
+    project_root: String,
+    referenced_paths: Vec<String>,
+    move_to_trash: Option<bool>,
+) -> Result<GcResult, String> {
+    // referenced_paths 为空通常意味着调用方还没加载任何文档（或传参出错），
+    // 此时继续执行会把 assets 目录下所有文件当成"未引用"而清空，这里直接拒绝
+    if referenced_paths.is_empty() {
+        return Err("referenced_paths 为空，拒绝执行 GC 以避免清空整个 assets 目录".to_string());
+    }
+
+    let assets_dir = Path::new(&project_root).join("assets");
+    if !assets_dir.exists() {
+        return Ok(GcResult {
+            removed: Vec::new(),
+            bytes_freed: 0,
+        });
+    }
+
+    let referenced: HashSet<String> = referenced_paths
+        .iter()
+        .filter_map(|p| {
+            Path::new(p)
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+        })
+        .collect();
+
+    // 默认先挪到回收站，只有显式传 false 才会直接硬删除
+    let move_to_trash = move_to_trash.unwrap_or(true);
+    let trash_dir = Path::new(&project_root).join("_trash");
+
+    let mut removed = Vec::new();
+    let mut bytes_freed = 0u64;
+
+    for entry in fs::read_dir(&assets_dir).map_err(|e| e.to_string())? {
+        let entry = entry.map_err(|e| e.to_string())?;
+        let path = entry.path();
+        if !path.is_file() {
+            continue;
+        }
+        let filename = match path.file_name().and_then(|n| n.to_str()) {
+            Some(f) => f.to_string(),
+            None => continue,
+        };
+        if referenced.contains(&filename) {
+            continue;
+        }
+
+        let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+
+        if move_to_trash {
+            if !trash_dir.exists() {
+                fs::create_dir_all(&trash_dir).map_err(|e| e.to_string())?;
+            }
+            fs::rename(&path, trash_dir.join(&filename)).map_err(|e| e.to_string())?;
+        } else {
+            fs::remove_file(&path).map_err(|e| e.to_string())?;
+        }
+
+        removed.push(format!("assets/{}", filename));
+        bytes_freed += size;
+    }
+
+    Ok(GcResult {
+        removed,
+        bytes_freed,
+    })
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
@@ -150,7 +372,7 @@ pub fn run() {
                 .build();
 
             // 将线程池托管给 Tauri
-            app.manage(ThumbnailCacheState { pool });
+            app.manage(ThumbnailCacheState::new(pool, DEFAULT_MAX_CACHE_BYTES));
             Ok(())
         })
         .register_asynchronous_uri_scheme_protocol("thumb", thumb_protocol::protocol_handler)
@@ -159,7 +381,15 @@ pub fn run() {
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_http::init())
         .plugin(tauri_plugin_opener::init())
-        .invoke_handler(tauri::generate_handler![save_temp_image, commit_assets])
+        .invoke_handler(tauri::generate_handler![
+            save_temp_image,
+            commit_assets,
+            get_cache_stats,
+            set_max_cache_bytes,
+            clear_thumb_cache,
+            find_similar_assets,
+            gc_assets
+        ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
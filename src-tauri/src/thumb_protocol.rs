@@ -1,12 +1,15 @@
 // src-tauri/src/thumb_protocol.rs
 use crate::utils::{get_temp_dir, get_thumb_cache_dir};
 use fast_image_resize::images::Image;
-use fast_image_resize::{IntoImageView, ResizeAlg, ResizeOptions, Resizer};
-use image::ImageReader;
+use fast_image_resize::{IntoImageView, PixelType, ResizeAlg, ResizeOptions, Resizer};
+use image::{DynamicImage, ImageBuffer, ImageReader, Rgb};
 use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use std::fs::{self};
-use std::io::BufWriter;
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
 use tauri::Wry;
 use tauri::{
     http::{header, Request, Response, StatusCode},
@@ -17,11 +20,98 @@ use threadpool::ThreadPool;
 use image::codecs::png::PngEncoder;
 use image::ImageEncoder;
 
+// 缩略图 WebP 有损编码质量 (0-100)，在体积和画质之间取的折中值
+const WEBP_QUALITY: f32 = 80.0;
+
 use std::time::Instant; // 引入计时器
 
+// 默认缩略图缓存上限：500MB
+pub const DEFAULT_MAX_CACHE_BYTES: u64 = 500 * 1024 * 1024;
+
+// 一个缩略图生成任务的完成信号：bool 为完成标记，Condvar 用于唤醒等待者
+type InflightSignal = Arc<(Mutex<bool>, Condvar)>;
+
 // 定义状态结构体 (需要在 lib.rs 中 pub 以便 manage)
 pub struct ThumbnailCacheState {
     pub pool: ThreadPool,
+    // 缓存总大小上限（字节），超出后按最旧访问时间淘汰；用 AtomicU64 以便运行时通过命令调整
+    max_cache_bytes: AtomicU64,
+    // 按 cache_key 合并同一张缩略图的并发请求，避免重复解码/缩放/编码
+    inflight: Mutex<HashMap<String, InflightSignal>>,
+}
+
+impl ThumbnailCacheState {
+    pub fn new(pool: ThreadPool, max_cache_bytes: u64) -> Self {
+        Self {
+            pool,
+            max_cache_bytes: AtomicU64::new(max_cache_bytes),
+            inflight: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn max_cache_bytes(&self) -> u64 {
+        self.max_cache_bytes.load(Ordering::Relaxed)
+    }
+
+    pub fn set_max_cache_bytes(&self, max_cache_bytes: u64) {
+        self.max_cache_bytes.store(max_cache_bytes, Ordering::Relaxed);
+    }
+}
+
+// 确保无论成功/失败/panic 退出，都会移除 inflight 记录并唤醒等待者
+struct InflightGuard<'a> {
+    state: &'a ThumbnailCacheState,
+    cache_key: String,
+}
+
+impl<'a> Drop for InflightGuard<'a> {
+    fn drop(&mut self) {
+        let mut inflight = self.state.inflight.lock().unwrap();
+        if let Some(signal) = inflight.remove(&self.cache_key) {
+            let (lock, cvar) = &*signal;
+            let mut done = lock.lock().unwrap();
+            *done = true;
+            cvar.notify_all();
+        }
+    }
+}
+
+// 缩略图输出编码格式
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ThumbFormat {
+    Png,
+    WebP,
+}
+
+impl ThumbFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            ThumbFormat::Png => "png",
+            ThumbFormat::WebP => "webp",
+        }
+    }
+
+    fn mime_type(self) -> &'static str {
+        match self {
+            ThumbFormat::Png => "image/png",
+            ThumbFormat::WebP => "image/webp",
+        }
+    }
+
+    // 解析 `fmt` 查询参数，再退而求其次看 Accept 头是否支持 webp
+    fn negotiate(fmt_param: Option<&str>, accept_header: Option<&str>) -> ThumbFormat {
+        match fmt_param {
+            Some("webp") => return ThumbFormat::WebP,
+            Some("png") => return ThumbFormat::Png,
+            _ => {}
+        }
+        if let Some(accept) = accept_header {
+            if accept.contains("image/webp") {
+                return ThumbFormat::WebP;
+            }
+        }
+        ThumbFormat::Png
+    }
 }
 
 // 获取文件 MIME Type
@@ -31,33 +121,121 @@ fn get_mime_type(path: &Path) -> String {
         .to_string()
 }
 
+// 相机 RAW 格式扩展名，image crate 无法直接解码这些
+const RAW_EXTENSIONS: &[&str] = &["nef", "cr2", "dng", "arw", "raf", "orf", "rw2"];
+
+fn is_raw_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|s| s.to_str())
+        .map(|ext| RAW_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+// 通过 rawloader 读取传感器数据，再用 imagepipe 去马赛克得到 8bit RGB 图像
+fn decode_raw_image(path: &Path) -> Result<DynamicImage, String> {
+    let raw_image =
+        rawloader::decode_file(path).map_err(|e| format!("Failed to decode RAW file: {}", e))?;
+    let mut pipeline = imagepipe::Pipeline::new_from_source(imagepipe::ImageSource::Raw(raw_image))
+        .map_err(|e| format!("Failed to build RAW pipeline: {}", e))?;
+    let decoded = pipeline
+        .output_8bit(None)
+        .map_err(|e| format!("Failed to process RAW pipeline: {}", e))?;
+    let buffer = ImageBuffer::<Rgb<u8>, Vec<u8>>::from_raw(
+        decoded.width as u32,
+        decoded.height as u32,
+        decoded.data,
+    )
+    .ok_or_else(|| "RAW pipeline produced an invalid buffer".to_string())?;
+    Ok(DynamicImage::ImageRgb8(buffer))
+}
+
+fn is_heif_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|s| s.to_str())
+        .map(|ext| {
+            let ext = ext.to_lowercase();
+            ext == "heic" || ext == "heif"
+        })
+        .unwrap_or(false)
+}
+
+// 解码 iPhone 拍摄的 HEIC/HEIF 照片，依赖系统 libheif，未开启 `heif` feature 时不会编译进来
+#[cfg(feature = "heif")]
+fn decode_heif_image(path: &Path) -> Result<DynamicImage, String> {
+    use libheif_rs::{ColorSpace, HeifContext, RgbChroma};
+
+    let ctx = HeifContext::read_from_file(&path.to_string_lossy())
+        .map_err(|e| format!("Failed to open HEIF file: {}", e))?;
+    let handle = ctx
+        .primary_image_handle()
+        .map_err(|e| format!("Failed to get primary HEIF image: {}", e))?;
+    let image = handle
+        .decode(ColorSpace::Rgb(RgbChroma::C444), None)
+        .map_err(|e| format!("Failed to decode HEIF image: {}", e))?;
+
+    let plane = image
+        .planes()
+        .interleaved
+        .ok_or_else(|| "HEIF image has no interleaved RGB plane".to_string())?;
+    let width = plane.width;
+    let height = plane.height;
+    let stride = plane.stride;
+
+    let mut data = Vec::with_capacity((width * height * 3) as usize);
+    for row in 0..height {
+        let start = (row as usize) * stride;
+        data.extend_from_slice(&plane.data[start..start + (width as usize * 3)]);
+    }
+
+    let buffer = ImageBuffer::<Rgb<u8>, Vec<u8>>::from_raw(width, height, data)
+        .ok_or_else(|| "HEIF decode produced an invalid buffer".to_string())?;
+    Ok(DynamicImage::ImageRgb8(buffer))
+}
+
+// process_thumbnail 的返回结果：原图走按需分片读取，缩略图走一次性内存返回
+enum ThumbOutput {
+    Original { path: PathBuf, mime_type: String },
+    Thumbnail { data: Vec<u8>, mime_type: String },
+}
+
 // 核心：处理图片
 fn process_thumbnail(
     app: &AppHandle,
     file_path: PathBuf,
     target_width: u32,
-) -> Result<(Vec<u8>, String), String> {
+    format: ThumbFormat,
+) -> Result<ThumbOutput, String> {
     if !file_path.exists() {
         return Err(format!("Source file not found: {:?}", file_path));
     }
 
+    let state = app.state::<ThumbnailCacheState>();
+
     let start_time = Instant::now(); // 计时
     let path_str = file_path.to_string_lossy();
-    let (orig_width, orig_height) = image::image_dimensions(&file_path)
-        .map_err(|_err| format!("无法获取图片宽度: {}", path_str))?;
-    // A. 原图请求
-    if target_width == 0 || orig_width < target_width {
-        let buffer = fs::read(&file_path).map_err(|e| e.to_string())?;
+
+    // A. 明确的原图请求（w=0）：不需要知道尺寸，也不用解码 RAW/HEIF，直接交给
+    // protocol_handler 按 Range 请求分片读取
+    if target_width == 0 {
         let mime_type = get_mime_type(&file_path);
         // println!("Original：{}", path_str);
-        return Ok((buffer, mime_type));
+        return Ok(ThumbOutput::Original {
+            path: file_path,
+            mime_type,
+        });
     }
 
-    // B. 缩略图请求
-    let cache_key = format!("{}?w={}", path_str, target_width);
+    // B. 缩略图请求：cache_key 只依赖路径/宽度/格式，解码前就能算出来，
+    // 所以缓存命中检查和并发合并都排在 RAW/HEIF 解码之前，避免每个等待者都白跑一次昂贵解码
+    let cache_key = format!("{}?w={}&fmt={}", path_str, target_width, format.extension());
     let mut hasher = Sha256::new();
     hasher.update(cache_key.as_bytes());
-    let filename = format!("{}_{}.png", hex::encode(hasher.finalize()), target_width);
+    let filename = format!(
+        "{}_{}.{}",
+        hex::encode(hasher.finalize()),
+        target_width,
+        format.extension()
+    );
 
     let thumb_dir = get_thumb_cache_dir(app);
     if !thumb_dir.exists() {
@@ -68,15 +246,107 @@ fn process_thumbnail(
     if cache_path.exists() {
         let buffer = fs::read(&cache_path).map_err(|e| e.to_string())?;
         // println!("Cache Hit：{}", cache_path.to_string_lossy());
-        return Ok((buffer, "image/png".to_string()));
+        return Ok(ThumbOutput::Thumbnail {
+            data: buffer,
+            mime_type: format.mime_type().to_string(),
+        });
+    }
+
+    // 合并同一 cache_key 的并发请求：先到者负责生成，后到者等待其完成后直接读缓存文件
+    let existing_signal = {
+        let mut inflight = state.inflight.lock().unwrap();
+        if let Some(signal) = inflight.get(&cache_key) {
+            Some(signal.clone())
+        } else {
+            inflight.insert(cache_key.clone(), Arc::new((Mutex::new(false), Condvar::new())));
+            None
+        }
+    };
+
+    if let Some(signal) = existing_signal {
+        let (lock, cvar) = &*signal;
+        let mut done = lock.lock().unwrap();
+        while !*done {
+            done = cvar.wait(done).unwrap();
+        }
+        drop(done);
+        if cache_path.exists() {
+            let buffer = fs::read(&cache_path).map_err(|e| e.to_string())?;
+            return Ok(ThumbOutput::Thumbnail {
+                data: buffer,
+                mime_type: format.mime_type().to_string(),
+            });
+        }
+        // 极少数情况：先到者最终判定该返回原图而非缩略图（源图小于目标宽度），
+        // 缓存文件不会被写入，重新走一次流程让本请求自己做出同样的判定
+        return process_thumbnail(app, file_path, target_width, format);
+    }
+
+    // 本请求是该 cache_key 的"先到者"，负责实际生成；无论后面如何返回都会唤醒等待者
+    let _inflight_guard = InflightGuard {
+        state: state.inner(),
+        cache_key: cache_key.clone(),
+    };
+
+    // RAW/HEIF 源文件需要先解码一次，后续 resize 直接复用解码结果，避免二次解码
+    let raw_decoded = if is_raw_file(&file_path) {
+        Some(decode_raw_image(&file_path)?)
+    } else if is_heif_file(&file_path) {
+        #[cfg(feature = "heif")]
+        {
+            Some(decode_heif_image(&file_path)?)
+        }
+        #[cfg(not(feature = "heif"))]
+        {
+            None
+        }
+    } else {
+        None
+    };
+
+    let (orig_width, orig_height) = if let Some(img) = &raw_decoded {
+        (img.width(), img.height())
+    } else {
+        image::image_dimensions(&file_path)
+            .map_err(|_err| format!("无法获取图片宽度: {}", path_str))?
+    };
+    // A'. 源图本身比请求宽度还小：返回原图，交给 protocol_handler 分片读取
+    // （inflight guard 在此 drop，等待者会被唤醒并发现缓存文件不存在，见上面的回退分支）
+    if orig_width < target_width {
+        let mime_type = get_mime_type(&file_path);
+        return Ok(ThumbOutput::Original {
+            path: file_path,
+            mime_type,
+        });
     }
 
     let open_start = Instant::now();
-    // 生成
-    let src_image = ImageReader::open(&file_path)
-        .map_err(|e| format!("Failed to open image: {}", e))?
-        .decode()
-        .unwrap();
+    // 生成（RAW/HEIF 文件复用上面已解码的结果，其余走 image crate 常规路径）
+    let src_image = if let Some(img) = raw_decoded {
+        img
+    } else {
+        ImageReader::open(&file_path)
+            .map_err(|e| format!("Failed to open image: {}", e))?
+            .decode()
+            .unwrap()
+    };
+
+    // WebP 编码只接受 8-bit RGB/RGBA buffer；灰度、16-bit 等源图的 pixel_type 是
+    // U8x1/U16x* 之类，若直接塞给 webp::Encoder::from_rgb/from_rgba 会按 w*h*3(/4)
+    // 字节去读一个更小的 buffer，越界读取。这里先转换成 rgb8/rgba8 再走后续流程
+    let src_image = if matches!(format, ThumbFormat::WebP)
+        && !matches!(
+            src_image.pixel_type(),
+            Some(PixelType::U8x3) | Some(PixelType::U8x4)
+        ) {
+        if src_image.color().has_alpha() {
+            DynamicImage::ImageRgba8(src_image.into_rgba8())
+        } else {
+            DynamicImage::ImageRgb8(src_image.into_rgb8())
+        }
+    } else {
+        src_image
+    };
 
     let target_height = (orig_height as f64 * target_width as f64 / orig_width as f64) as u32;
     let mut dst_image = Image::new(target_width, target_height, src_image.pixel_type().unwrap());
@@ -100,19 +370,34 @@ fn process_thumbnail(
     let encode_start = Instant::now();
     // let file = File::create(cache_path);
     let mut result_buf = BufWriter::new(Vec::new());
-    PngEncoder::new(&mut result_buf)
-        .write_image(
-            dst_image.buffer(),
-            target_width,
-            target_height,
-            src_image.color().into(),
-        )
-        .unwrap();
+    match format {
+        ThumbFormat::Png => {
+            PngEncoder::new(&mut result_buf)
+                .write_image(
+                    dst_image.buffer(),
+                    target_width,
+                    target_height,
+                    src_image.color().into(),
+                )
+                .unwrap();
+        }
+        ThumbFormat::WebP => {
+            // 有损编码：photo 类缩略图用 PNG 1/4~1/5 的体积，画质损失可忽略
+            let webp_encoder = if src_image.color().has_alpha() {
+                webp::Encoder::from_rgba(dst_image.buffer(), target_width, target_height)
+            } else {
+                webp::Encoder::from_rgb(dst_image.buffer(), target_width, target_height)
+            };
+            let encoded = webp_encoder.encode(WEBP_QUALITY);
+            result_buf.write_all(&encoded).unwrap();
+        }
+    }
 
     let encode_time = encode_start.elapsed();
     let buffer = result_buf.into_inner().unwrap();
 
     fs::write(&cache_path, buffer.clone()).map_err(|e| e.to_string())?;
+    evict_cache_if_needed(&thumb_dir, state.max_cache_bytes());
     println!(
         "{}->{}，总耗时：{:?}，打开：{:?}，缩放：{:?}，编码：{:?}",
         orig_width,
@@ -122,7 +407,200 @@ fn process_thumbnail(
         resize_time,
         encode_time
     );
-    Ok((buffer, "image/png".to_string()))
+    Ok(ThumbOutput::Thumbnail {
+        data: buffer,
+        mime_type: format.mime_type().to_string(),
+    })
+}
+
+// 按最近访问时间排序，淘汰最旧的缓存文件直到总大小回落到上限之下
+fn evict_cache_if_needed(thumb_dir: &Path, max_cache_bytes: u64) {
+    let entries = match fs::read_dir(thumb_dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    let mut files: Vec<(PathBuf, u64, std::time::SystemTime)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let meta = entry.metadata().ok()?;
+            if !meta.is_file() {
+                return None;
+            }
+            let accessed = meta
+                .accessed()
+                .or_else(|_| meta.modified())
+                .unwrap_or(std::time::SystemTime::UNIX_EPOCH);
+            Some((entry.path(), meta.len(), accessed))
+        })
+        .collect();
+
+    let mut total: u64 = files.iter().map(|(_, size, _)| size).sum();
+    if total <= max_cache_bytes {
+        return;
+    }
+
+    // 最旧的排在前面，优先淘汰
+    files.sort_by_key(|(_, _, accessed)| *accessed);
+
+    for (path, size, _) in files {
+        if total <= max_cache_bytes {
+            break;
+        }
+        if fs::remove_file(&path).is_ok() {
+            total = total.saturating_sub(size);
+        }
+    }
+}
+
+// 统计缓存目录的文件数量和总大小，供 `get_cache_stats` 命令使用
+pub fn get_cache_dir_stats(thumb_dir: &Path) -> (u64, usize) {
+    let entries = match fs::read_dir(thumb_dir) {
+        Ok(entries) => entries,
+        Err(_) => return (0, 0),
+    };
+
+    let mut total_bytes = 0u64;
+    let mut file_count = 0usize;
+    for entry in entries.filter_map(|e| e.ok()) {
+        if let Ok(meta) = entry.metadata() {
+            if meta.is_file() {
+                total_bytes += meta.len();
+                file_count += 1;
+            }
+        }
+    }
+    (total_bytes, file_count)
+}
+
+// Range 头解析结果：区分"没有/无法解析的 Range"（按整文件处理）和"语法合法但越界"（416）
+enum RangeOutcome {
+    Full,
+    Satisfiable(u64, u64),
+    Unsatisfiable,
+}
+
+// 解析形如 `bytes=start-end` 的 Range 头
+fn resolve_range(header_value: Option<&str>, file_size: u64) -> RangeOutcome {
+    let Some(header_value) = header_value else {
+        return RangeOutcome::Full;
+    };
+    let Some(spec) = header_value.strip_prefix("bytes=") else {
+        return RangeOutcome::Full;
+    };
+    let Some((start_str, end_str)) = spec.split_once('-') else {
+        return RangeOutcome::Full;
+    };
+
+    // start 为空、end 非空是后缀范围写法（如 bytes=-500 表示最后 500 字节），
+    // 与"从 0 开始"是完全不同的语义，必须单独处理
+    if start_str.is_empty() {
+        return match end_str.parse::<u64>() {
+            Ok(_) if file_size == 0 => RangeOutcome::Unsatisfiable,
+            Ok(suffix_len) if suffix_len > 0 => RangeOutcome::Satisfiable(
+                file_size.saturating_sub(suffix_len.min(file_size)),
+                file_size - 1,
+            ),
+            _ => RangeOutcome::Full,
+        };
+    }
+
+    let start: Option<u64> = start_str.parse().ok();
+    let end: Option<u64> = if end_str.is_empty() {
+        Some(file_size.saturating_sub(1))
+    } else {
+        end_str.parse().ok()
+    };
+
+    // Range 头语法本身不合法：按 RFC 7233 可以忽略它，退化为整文件响应
+    let (Some(start), Some(end)) = (start, end) else {
+        return RangeOutcome::Full;
+    };
+
+    // 语法合法但区间越界（如 bytes=99999-）：必须 416，而不是悄悄返回整个文件
+    if file_size == 0 || start >= file_size || start > end {
+        return RangeOutcome::Unsatisfiable;
+    }
+    RangeOutcome::Satisfiable(start, end.min(file_size - 1))
+}
+
+// 将原图按 Range 请求分片读取并返回，没有 Range 头时退化为整文件 200 响应
+fn respond_with_range(
+    responder: tauri::UriSchemeResponder,
+    path: &Path,
+    mime_type: &str,
+    range_header: Option<&str>,
+) {
+    let file_size = match fs::metadata(path) {
+        Ok(meta) => meta.len(),
+        Err(e) => {
+            responder.respond(
+                Response::builder()
+                    .status(StatusCode::INTERNAL_SERVER_ERROR)
+                    .body(e.to_string().into_bytes())
+                    .unwrap(),
+            );
+            return;
+        }
+    };
+
+    let range = resolve_range(range_header, file_size);
+
+    if let RangeOutcome::Unsatisfiable = range {
+        responder.respond(
+            Response::builder()
+                .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                .header(header::CONTENT_RANGE, format!("bytes */{}", file_size))
+                .body(Vec::new())
+                .unwrap(),
+        );
+        return;
+    }
+
+    let result = (|| -> Result<Response<Vec<u8>>, std::io::Error> {
+        if let RangeOutcome::Satisfiable(start, end) = range {
+            let length = end - start + 1;
+            let mut file = fs::File::open(path)?;
+            file.seek(SeekFrom::Start(start))?;
+            let mut buffer = vec![0u8; length as usize];
+            file.read_exact(&mut buffer)?;
+
+            Ok(Response::builder()
+                .status(StatusCode::PARTIAL_CONTENT)
+                .header(header::CONTENT_TYPE, mime_type)
+                .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+                .header(header::CACHE_CONTROL, "public, max-age=31536000")
+                .header(header::ACCEPT_RANGES, "bytes")
+                .header(
+                    header::CONTENT_RANGE,
+                    format!("bytes {}-{}/{}", start, end, file_size),
+                )
+                .header(header::CONTENT_LENGTH, length.to_string())
+                .body(buffer)
+                .unwrap())
+        } else {
+            let buffer = fs::read(path)?;
+            Ok(Response::builder()
+                .status(StatusCode::OK)
+                .header(header::CONTENT_TYPE, mime_type)
+                .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+                .header(header::CACHE_CONTROL, "public, max-age=31536000")
+                .header(header::ACCEPT_RANGES, "bytes")
+                .header(header::CONTENT_LENGTH, buffer.len().to_string())
+                .body(buffer)
+                .unwrap())
+        }
+    })();
+
+    match result {
+        Ok(response) => responder.respond(response),
+        Err(e) => responder.respond(
+            Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body(e.to_string().into_bytes())
+                .unwrap(),
+        ),
+    }
 }
 
 // 路径路由
@@ -157,11 +635,22 @@ pub fn protocol_handler(
     let app_worker = app.clone();
     let state = app.state::<ThumbnailCacheState>();
     let uri = request.uri().clone();
+    let accept_header = request
+        .headers()
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string());
+    let range_header = request
+        .headers()
+        .get(header::RANGE)
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string());
 
     state.pool.execute(move || {
         let uri_str = uri.to_string();
         let mut target_width = 0;
         let mut project_root: Option<String> = None;
+        let mut fmt_param: Option<String> = None;
 
         let parts: Vec<&str> = uri_str.split('?').collect();
         let raw_url_path = parts[0];
@@ -176,12 +665,15 @@ pub fn protocol_handler(
                                 project_root = Some(decoded_root.to_string());
                             }
                         }
+                        "fmt" => fmt_param = Some(v.to_string()),
                         _ => {}
                     }
                 }
             }
         }
 
+        let format = ThumbFormat::negotiate(fmt_param.as_deref(), accept_header.as_deref());
+
         let path_part = if let Some(p) = raw_url_path.strip_prefix("thumb://localhost/") {
             p
         } else if let Some(p) = raw_url_path.strip_prefix("thumb://") {
@@ -197,8 +689,9 @@ pub fn protocol_handler(
         let real_path_opt = resolve_real_path(&app_worker, &decoded_path_str, project_root);
 
         match real_path_opt {
-            Some(real_path) => match process_thumbnail(&app_worker, real_path, target_width) {
-                Ok((data, mime_type)) => responder.respond(
+            Some(real_path) => match process_thumbnail(&app_worker, real_path, target_width, format)
+            {
+                Ok(ThumbOutput::Thumbnail { data, mime_type }) => responder.respond(
                     Response::builder()
                         .status(StatusCode::OK)
                         .header(header::CONTENT_TYPE, mime_type)
@@ -207,6 +700,9 @@ pub fn protocol_handler(
                         .body(data)
                         .unwrap(),
                 ),
+                Ok(ThumbOutput::Original { path, mime_type }) => {
+                    respond_with_range(responder, &path, &mime_type, range_header.as_deref())
+                }
                 Err(e) => {
                     eprintln!("Thumb Error: {}", e);
                     responder.respond(